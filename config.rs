@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Health cutoffs consumed by `probe_data::determine_health` and the
+/// optimizer — previously hardcoded in both places.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Thresholds {
+    pub packet_loss_percent: f64,
+    pub latency_ms: f64,
+    pub signal_strength_percent: f64,
+    pub download_speed_mbps: f64,
+    pub jitter_ms: f64,
+    pub bandwidth_hog_mbps: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            packet_loss_percent: 5.0,
+            latency_ms: 150.0,
+            signal_strength_percent: 50.0,
+            download_speed_mbps: 10.0,
+            jitter_ms: 10.0,
+            bandwidth_hog_mbps: 5.0,
+        }
+    }
+}
+
+/// Interface and connection names the optimizer shells out against — these
+/// vary per machine, so they can't stay hardcoded as `eth0`/`wlan0`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interfaces {
+    pub primary: String,
+    pub wireless: String,
+    pub backup_connection: String,
+}
+
+impl Default for Interfaces {
+    fn default() -> Self {
+        Self {
+            primary: "eth0".to_string(),
+            wireless: "wlan0".to_string(),
+            backup_connection: "backup-connection".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub interfaces: Interfaces,
+    #[serde(default)]
+    pub thresholds: Thresholds,
+}
+
+impl Config {
+    const DEFAULT_PATH: &'static str = "config.toml";
+
+    /// Loads `config.toml`, or — if it's missing or `--wizard` was passed —
+    /// runs an interactive first-run flow that probes available interfaces
+    /// and asks for thresholds, then writes the file.
+    pub fn load_or_wizard(args: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::DEFAULT_PATH;
+        if args.iter().any(|a| a == "--wizard") || !Path::new(path).exists() {
+            let config = Self::run_wizard()?;
+            config.save(path)?;
+            return Ok(config);
+        }
+        Self::load(path)
+    }
+
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn run_wizard() -> Result<Self, Box<dyn std::error::Error>> {
+        println!("No config.toml found — let's set one up.");
+        let detected = detect_interfaces();
+        if !detected.is_empty() {
+            println!("Detected interfaces: {}", detected.join(", "));
+        }
+
+        let interfaces = Interfaces {
+            primary: prompt("Primary (wired) interface", "eth0")?,
+            wireless: prompt("Wireless interface", "wlan0")?,
+            backup_connection: prompt("Backup nmcli connection name", "backup-connection")?,
+        };
+
+        let thresholds = Thresholds {
+            packet_loss_percent: prompt_f64("Packet loss % threshold (critical above)", 5.0)?,
+            latency_ms: prompt_f64("Latency ms threshold (critical above)", 150.0)?,
+            signal_strength_percent: prompt_f64("Signal strength % threshold (poor below)", 50.0)?,
+            download_speed_mbps: prompt_f64("Download Mbps threshold (poor below)", 10.0)?,
+            jitter_ms: prompt_f64("Jitter ms threshold (fair above)", 10.0)?,
+            bandwidth_hog_mbps: prompt_f64("Per-process Mbps threshold before throttling", 5.0)?,
+        };
+
+        Ok(Config { interfaces, thresholds })
+    }
+}
+
+fn detect_interfaces() -> Vec<String> {
+    fs::read_to_string("/proc/net/dev")
+        .map(|contents| {
+            contents
+                .lines()
+                .skip(2)
+                .filter_map(|line| line.split_once(':').map(|(name, _)| name.trim().to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn prompt(label: &str, default: &str) -> io::Result<String> {
+    print!("{label} [{default}]: ");
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let trimmed = input.trim();
+    Ok(if trimmed.is_empty() {
+        default.to_string()
+    } else {
+        trimmed.to_string()
+    })
+}
+
+fn prompt_f64(label: &str, default: f64) -> io::Result<f64> {
+    let raw = prompt(label, &default.to_string())?;
+    Ok(raw.trim().parse().unwrap_or(default))
+}
@@ -1,10 +1,16 @@
+use std::collections::VecDeque;
 use std::error::Error;
 use std::io;
+use std::time::{Duration, Instant};
 
+mod config;
+mod models;
 mod probe_data;
 mod optimizer;
+mod top_talkers;
 
 #[cfg(feature = "api")]
+#[path = "mod.rs"]
 mod api;
 
 use crossterm::{
@@ -16,17 +22,26 @@ use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Layout},
     style::{Color, Style},
-    widgets::{Block, Borders, Row, Table},
+    widgets::{Block, Borders, Row, Sparkline, Table},
     Terminal,
 };
 
+use probe_data::{Averages, HealthStatus, LiveSampler};
+use top_talkers::ProcessBandwidth;
+
+const HISTORY_LEN: usize = 120;
+// Latency, jitter, and signal strength are excluded here: LiveSampler derives
+// its averages from /proc/net/dev and /proc/net/snmp alone, which can never
+// produce those three (see probe_data::derive_averages), so sparklines and
+// the enlarge-cycle would always be empty for them.
+const METRICS: [Metric; 3] = [Metric::PacketLoss, Metric::DownloadSpeed, Metric::UploadSpeed];
+
 #[cfg(not(feature = "api"))]
 fn main() -> Result<(), Box<dyn Error>> {
     // TUI mode when api feature is not enabled
-    let file_path = "data.csv";
-    let all_data = probe_data::read_csv(file_path)?;
-    let recent_data = probe_data::filter_last_n_days(&all_data, 3);
-    let averages = probe_data::calculate_averages(&recent_data);
+    let args: Vec<String> = std::env::args().collect();
+    let config = config::Config::load_or_wizard(&args)?;
+    let sampler = LiveSampler::new(config.interfaces.primary.clone(), Duration::from_secs(2));
 
     // Setup terminal UI
     enable_raw_mode()?;
@@ -36,7 +51,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // Run the user interface
-    let result = run_ui(&mut terminal, averages);
+    let result = run_ui(&mut terminal, sampler, config);
 
     // Cleanup terminal
     disable_raw_mode()?;
@@ -54,23 +69,133 @@ fn main() -> Result<(), Box<dyn Error>> {
 #[cfg(feature = "api")]
 fn main() -> Result<(), Box<dyn Error>> {
     // API mode - use actix's runtime instead of tokio directly
-    let optimizer = std::sync::Arc::new(std::sync::Mutex::new(optimizer::NetworkOptimizer::new()));
-    
+    let args: Vec<String> = std::env::args().collect();
+    let config = config::Config::load_or_wizard(&args)?;
+    let optimizer = std::sync::Arc::new(std::sync::Mutex::new(optimizer::NetworkOptimizer::new(config.clone())));
+    let store = std::sync::Arc::new(std::sync::Mutex::new(probe_data::Store::open("history.db")?));
+    let live_sampler = std::sync::Arc::new(std::sync::Mutex::new(LiveSampler::new(
+        config.interfaces.primary.clone(),
+        Duration::from_secs(2),
+    )));
+
     actix_web::rt::System::new().block_on(async {
-        api::run(optimizer).await
+        api::run(optimizer, store, config, live_sampler).await
     })?;
-    
+
     Ok(())
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Metric {
+    PacketLoss,
+    DownloadSpeed,
+    UploadSpeed,
+}
+
+impl Metric {
+    fn label(&self) -> &'static str {
+        match self {
+            Metric::PacketLoss => "Packet Loss (%)",
+            Metric::DownloadSpeed => "Download Speed (Mbps)",
+            Metric::UploadSpeed => "Upload Speed (Mbps)",
+        }
+    }
+
+    fn value(&self, averages: &Averages) -> Option<f64> {
+        match self {
+            Metric::PacketLoss => averages.packet_loss,
+            Metric::DownloadSpeed => averages.download_speed,
+            Metric::UploadSpeed => averages.upload_speed,
+        }
+    }
+
+    fn next(&self) -> Metric {
+        let idx = METRICS.iter().position(|m| m == self).unwrap();
+        METRICS[(idx + 1) % METRICS.len()]
+    }
+
+    fn prev(&self) -> Metric {
+        let idx = METRICS.iter().position(|m| m == self).unwrap();
+        METRICS[(idx + METRICS.len() - 1) % METRICS.len()]
+    }
+}
+
+/// Ring buffers of recent samples, one per metric, for the sparkline view.
+struct History {
+    buffers: [VecDeque<u64>; 3],
+}
+
+impl History {
+    fn new() -> Self {
+        Self {
+            buffers: Default::default(),
+        }
+    }
+
+    fn push(&mut self, averages: &Averages) {
+        for (buffer, metric) in self.buffers.iter_mut().zip(METRICS.iter()) {
+            if let Some(value) = metric.value(averages) {
+                buffer.push_back(value.max(0.0).round() as u64);
+                if buffer.len() > HISTORY_LEN {
+                    buffer.pop_front();
+                }
+            }
+        }
+    }
+
+    fn data(&self, metric: Metric) -> &VecDeque<u64> {
+        let idx = METRICS.iter().position(|m| *m == metric).unwrap();
+        &self.buffers[idx]
+    }
+}
+
+fn health_color(status: &HealthStatus) -> Color {
+    match status {
+        HealthStatus::Excellent | HealthStatus::Good => Color::Green,
+        HealthStatus::Fair => Color::Yellow,
+        HealthStatus::Poor => Color::Rgb(255, 140, 0),
+        HealthStatus::Critical => Color::Red,
+    }
+}
+
 fn run_ui<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
-    averages: probe_data::Averages,
+    mut sampler: LiveSampler,
+    config: config::Config,
 ) -> io::Result<()> {
+    let mut history = History::new();
+    let mut averages = Averages {
+        latency: None,
+        jitter: None,
+        packet_loss: None,
+        signal_strength: None,
+        download_speed: None,
+        upload_speed: None,
+    };
+    let mut health = probe_data::determine_health(&averages, &config.thresholds);
+    let mut enlarged = Metric::PacketLoss;
+    let mut paused = false;
+    let mut last_sample = Instant::now() - sampler.interval();
+    let mut top_talkers: Vec<ProcessBandwidth> = Vec::new();
+    let mut show_top_talkers = false;
+
     loop {
+        if !paused && last_sample.elapsed() >= sampler.interval() {
+            if let Ok(Some(sample)) = sampler.sample() {
+                history.push(&sample);
+                averages = sample;
+                health = probe_data::determine_health(&averages, &config.thresholds);
+            }
+            last_sample = Instant::now();
+        }
+
         terminal.draw(|frame| {
             let layout = Layout::default()
-                .constraints([Constraint::Percentage(100)])
+                .constraints([
+                    Constraint::Length(8),
+                    Constraint::Min(3),
+                    Constraint::Length(1),
+                ])
                 .split(frame.size());
 
             let latency_value = display_opt(averages.latency);
@@ -90,17 +215,78 @@ fn run_ui<B: ratatui::backend::Backend>(
             ];
 
             let table = Table::new(rows, [Constraint::Percentage(50), Constraint::Percentage(50)])
-                .block(Block::default().borders(Borders::ALL).title("Network Averages (Last 3 Days)"))
+                .block(Block::default().borders(Borders::ALL).title("Network Averages (Live)"))
                 .column_spacing(2)
                 .style(Style::default().fg(Color::White));
 
             frame.render_widget(table, layout[0]);
+
+            if show_top_talkers {
+                let rows: Vec<Row> = top_talkers
+                    .iter()
+                    .map(|p| {
+                        Row::new(vec![
+                            format!("{} ({})", p.process_name, p.pid),
+                            format!("{:.2}", p.download_mbps),
+                            format!("{:.2}", p.upload_mbps),
+                        ])
+                    })
+                    .collect();
+                let table = Table::new(
+                    rows,
+                    [Constraint::Percentage(50), Constraint::Percentage(25), Constraint::Percentage(25)],
+                )
+                .header(Row::new(vec!["Process (PID)", "Down Mbps", "Up Mbps"]))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title("Top talkers (t to go back)"),
+                )
+                .style(Style::default().fg(Color::White));
+                frame.render_widget(table, layout[1]);
+            } else {
+                let data: Vec<u64> = history.data(enlarged).iter().copied().collect();
+                let sparkline = Sparkline::default()
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title(format!("{} — history (←/→ to cycle, t: top talkers)", enlarged.label())),
+                    )
+                    .data(&data)
+                    .style(Style::default().fg(Color::Cyan));
+                frame.render_widget(sparkline, layout[1]);
+            }
+
+            let health_text = if paused {
+                "PAUSED".to_string()
+            } else {
+                format!("{:?}", health)
+            };
+            let status_text = format!(
+                " {}  |  q: quit  p: {}  ←/→: cycle metric  t: top talkers",
+                health_text,
+                if paused { "resume" } else { "pause" }
+            );
+            let status = Row::new(vec![status_text.clone()]);
+            let status_table = Table::new(vec![status], [Constraint::Percentage(100)])
+                .style(Style::default().fg(health_color(&health)));
+            frame.render_widget(status_table, layout[2]);
         })?;
 
-        if event::poll(std::time::Duration::from_millis(250))? {
+        if event::poll(Duration::from_millis(250))? {
             if let Event::Key(key) = event::read()? {
-                if key.code == KeyCode::Char('q') {
-                    break;
+                match key.code {
+                    KeyCode::Char('q') => break,
+                    KeyCode::Char('p') => paused = !paused,
+                    KeyCode::Right | KeyCode::Tab => enlarged = enlarged.next(),
+                    KeyCode::Left => enlarged = enlarged.prev(),
+                    KeyCode::Char('t') => {
+                        show_top_talkers = !show_top_talkers;
+                        if show_top_talkers {
+                            top_talkers = crate::top_talkers::rank_processes(Duration::from_millis(500));
+                        }
+                    }
+                    _ => {}
                 }
             }
         }
@@ -1,14 +1,66 @@
-use actix_web::{get, post, web, App, HttpServer, Responder};
+use actix_web::{get, post, web, App, HttpResponse, HttpServer, Responder};
+use futures_util::StreamExt;
 use serde_json::json;
 use std::sync::{Arc, Mutex};
-use crate::{optimizer::NetworkOptimizer, probe_data};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+use crate::{
+    config::Config,
+    models::{HistoricalDataRequest, NetworkHealthResponse, NetworkMetrics, NetworkStatus},
+    optimizer::NetworkOptimizer,
+    probe_data,
+    probe_data::{Averages, HealthStatus, LiveSampler, Store},
+};
+
+/// How often the background loop samples and re-evaluates network health
+/// without anyone having hit `/analyze`.
+const AUTONOMOUS_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The most recent autonomous evaluation, shared between the background
+/// loop, `/network-status`, and `/stream`.
+type SharedLatest = Arc<Mutex<Option<NetworkHealthResponse>>>;
+
+// Handlers extract these as `web::Data<SharedX>`, not `web::Data<Mutex<X>>`:
+// `web::Data::new(arc.clone())` wraps the `Arc` itself in `Data`, so the
+// extractor's type has to name the `Arc` too, or actix's app-data lookup
+// (keyed by `TypeId`) never matches and every handler 500s.
+type SharedOptimizer = Arc<Mutex<NetworkOptimizer>>;
+type SharedStore = Arc<Mutex<Store>>;
+type SharedLiveSampler = Arc<Mutex<LiveSampler>>;
+
+pub async fn run(
+    optimizer: SharedOptimizer,
+    store: SharedStore,
+    config: Config,
+    live_sampler: SharedLiveSampler,
+) -> std::io::Result<()> {
+    let latest: SharedLatest = Arc::new(Mutex::new(None));
+    let (updates, _) = broadcast::channel::<NetworkHealthResponse>(16);
+
+    spawn_autonomous_loop(
+        optimizer.clone(),
+        store.clone(),
+        config.clone(),
+        live_sampler.clone(),
+        latest.clone(),
+        updates.clone(),
+    );
 
-pub async fn run(optimizer: Arc<Mutex<NetworkOptimizer>>) -> std::io::Result<()> {
     let server = HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(optimizer.clone()))
+            .app_data(web::Data::new(store.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .app_data(web::Data::new(live_sampler.clone()))
+            .app_data(web::Data::new(latest.clone()))
+            .app_data(web::Data::new(updates.clone()))
             .service(get_network_status)
             .service(analyze_network)
+            .service(history)
+            .service(top_talkers)
+            .service(stream)
     })
     .bind("127.0.0.1:8080")?;
 
@@ -16,32 +68,156 @@ pub async fn run(optimizer: Arc<Mutex<NetworkOptimizer>>) -> std::io::Result<()>
     server.run().await
 }
 
+/// Samples, evaluates health, and applies optimizations on a fixed cadence
+/// so the dashboard reflects reality even when nobody calls `/analyze`.
+fn spawn_autonomous_loop(
+    optimizer: SharedOptimizer,
+    store: SharedStore,
+    config: Config,
+    live_sampler: SharedLiveSampler,
+    latest: SharedLatest,
+    updates: broadcast::Sender<NetworkHealthResponse>,
+) {
+    actix_web::rt::spawn(async move {
+        let mut ticker = actix_web::rt::time::interval(AUTONOMOUS_INTERVAL);
+        loop {
+            ticker.tick().await;
+
+            let optimizer = optimizer.clone();
+            let store = store.clone();
+            let config = config.clone();
+            let live_sampler = live_sampler.clone();
+
+            let result = web::block(move || evaluate_once(&optimizer, &store, &config, &live_sampler)).await;
+
+            match result {
+                Ok(Ok(response)) => {
+                    *latest.lock().unwrap() = Some(response.clone());
+                    let _ = updates.send(response);
+                }
+                Ok(Err(e)) => eprintln!("Autonomous evaluation failed: {e}"),
+                Err(e) => eprintln!("Autonomous evaluation panicked: {e}"),
+            }
+        }
+    });
+}
+
+/// Takes a fresh live reading, described as an error (rather than silently
+/// skipping) when there's no predecessor yet to diff against or `/proc` is
+/// unreadable (e.g. this isn't Linux).
+fn sample_live(sampler: &Mutex<LiveSampler>) -> Result<Averages, String> {
+    let mut sampler = sampler.lock().unwrap();
+    match sampler.sample() {
+        Ok(Some(averages)) => Ok(averages),
+        Ok(None) => Err(
+            "No live sample yet — the sampler needs two polls to compute a delta; retry shortly"
+                .to_string(),
+        ),
+        Err(e) => Err(format!("Live sampling failed: {e}")),
+    }
+}
+
+/// One sample-evaluate-optimize cycle, run on a blocking thread since it
+/// shells out and touches SQLite.
+fn evaluate_once(
+    optimizer: &Mutex<NetworkOptimizer>,
+    store: &Mutex<Store>,
+    config: &Config,
+    live_sampler: &Mutex<LiveSampler>,
+) -> Result<NetworkHealthResponse, String> {
+    let averages = sample_live(live_sampler)?;
+    let status = probe_data::determine_health(&averages, &config.thresholds);
+
+    if let Err(e) = store.lock().unwrap().insert_sample(chrono::Utc::now(), &averages) {
+        eprintln!("Failed to persist autonomous sample: {e}");
+    }
+
+    let mut resample = || -> Option<Averages> { sample_live(live_sampler).ok() };
+
+    let mut optimizer = optimizer.lock().unwrap();
+    optimizer.apply_optimizations(
+        &probe_data::NetworkHealth { averages: averages.clone(), status },
+        &mut resample,
+    );
+
+    Ok(NetworkHealthResponse {
+        status: to_network_status(status),
+        metrics: to_network_metrics(&averages),
+        optimizations: optimizer.get_current_optimizations().clone(),
+        timestamp: chrono::Utc::now(),
+        time_range_seconds: AUTONOMOUS_INTERVAL.as_secs(),
+    })
+}
+
+fn to_network_status(status: HealthStatus) -> NetworkStatus {
+    match status {
+        HealthStatus::Excellent => NetworkStatus::Excellent,
+        HealthStatus::Good => NetworkStatus::Good,
+        HealthStatus::Fair => NetworkStatus::Fair,
+        HealthStatus::Poor => NetworkStatus::Poor,
+        HealthStatus::Critical => NetworkStatus::Critical,
+    }
+}
+
+fn to_network_metrics(averages: &Averages) -> NetworkMetrics {
+    NetworkMetrics {
+        latency_ms: averages.latency,
+        jitter_ms: averages.jitter,
+        packet_loss_percent: averages.packet_loss,
+        signal_strength_percent: averages.signal_strength,
+        download_speed_mbps: averages.download_speed,
+        upload_speed_mbps: averages.upload_speed,
+        gateway_reachable: averages.latency.is_some() || averages.packet_loss.is_some(),
+    }
+}
+
 #[post("/analyze")]
 async fn analyze_network(
-    optimizer: web::Data<Mutex<NetworkOptimizer>>,
+    optimizer: web::Data<SharedOptimizer>,
+    store: web::Data<SharedStore>,
+    config: web::Data<Config>,
+    live_sampler: web::Data<SharedLiveSampler>,
 ) -> impl Responder {
-    // Load and process data
-    let data = match probe_data::read_csv("data.csv") {
-        Ok(data) => data,
+    // Take a live reading instead of depending on a pre-existing CSV on disk
+    let averages = match sample_live(&live_sampler) {
+        Ok(averages) => averages,
         Err(e) => {
             return web::Json(json!({
-                "error": format!("Failed to load data: {}", e),
-                "details": "Check if data.csv exists and is properly formatted"
+                "error": e,
+                "details": "The live sampler polls /proc/net counters and needs two polls to compute a delta"
             }))
         }
     };
+    let interval_secs = live_sampler.lock().unwrap().interval().as_secs();
 
-    // Filter and analyze
-    let recent_data = probe_data::filter_last_n_days(&data, 3);
-    let averages = probe_data::calculate_averages(&recent_data);
-    let health_status = probe_data::determine_health(&averages);
+    let health_status = probe_data::determine_health(&averages, &config.thresholds);
 
-    // Apply optimizations
-    let mut optimizer = optimizer.lock().unwrap();
-    optimizer.apply_optimizations(&probe_data::NetworkHealth {
-        averages: averages.clone(),
-        status: health_status,
-    });
+    // Persist this sample so /history has something to aggregate later
+    if let Err(e) = store.lock().unwrap().insert_sample(chrono::Utc::now(), &averages) {
+        eprintln!("Failed to persist sample: {e}");
+    }
+
+    // Apply optimizations on a blocking thread: run_verified sleeps out
+    // SETTLE_INTERVAL per triggered optimization, which would otherwise
+    // stall this actix worker for seconds at a time.
+    let optimizer_data = optimizer.clone();
+    let live_sampler_data = live_sampler.clone();
+    let health_for_block = health_status;
+    let averages_for_block = averages.clone();
+    let current_optimizations = web::block(move || {
+        let mut optimizer = optimizer_data.lock().unwrap();
+        let mut resample = || -> Option<probe_data::Averages> { sample_live(&live_sampler_data).ok() };
+        optimizer.apply_optimizations(
+            &probe_data::NetworkHealth {
+                averages: averages_for_block,
+                status: health_for_block,
+            },
+            &mut resample,
+        );
+        optimizer.get_current_optimizations().clone()
+    })
+    .await
+    .unwrap_or_default();
 
     // Prepare response
     web::Json(json!({
@@ -54,20 +230,83 @@ async fn analyze_network(
             "upload_speed_mbps": averages.upload_speed,
         },
         "health_status": format!("{:?}", health_status),
-        "optimizations": optimizer.get_current_optimizations(),
+        "optimizations": current_optimizations,
         "timestamp": chrono::Utc::now().to_rfc3339(),
-        "time_range_seconds": 3 * 24 * 60 * 60  // 3 days in seconds
+        "time_range_seconds": interval_secs
     }))
 }
 
 #[get("/network-status")]
 async fn get_network_status(
-    optimizer: web::Data<Mutex<NetworkOptimizer>>,
+    optimizer: web::Data<SharedOptimizer>,
+    latest: web::Data<SharedLatest>,
 ) -> impl Responder {
     let optimizer = optimizer.lock().unwrap();
+    let latest = latest.lock().unwrap();
     web::Json(json!({
         "active_optimizations": optimizer.get_current_optimizations(),
         "failed_optimizations": optimizer.get_failed_optimizations(),
-        "last_updated": chrono::Utc::now().to_rfc3339()
+        "last_autonomous_evaluation": latest.as_ref(),
+        "last_updated": latest.as_ref()
+            .map(|r| r.timestamp.to_rfc3339())
+            .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+    }))
+}
+
+#[get("/stream")]
+async fn stream(updates: web::Data<broadcast::Sender<NetworkHealthResponse>>) -> impl Responder {
+    let rx = updates.subscribe();
+    let body = BroadcastStream::new(rx).filter_map(|update| async move {
+        let response = update.ok()?; // receiver lagged; skip to the next update
+        let payload = serde_json::to_string(&response).unwrap_or_default();
+        Some(Ok::<_, actix_web::Error>(web::Bytes::from(format!("data: {payload}\n\n"))))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .streaming(body)
+}
+
+#[post("/history")]
+async fn history(
+    req: web::Json<HistoricalDataRequest>,
+    store: web::Data<SharedStore>,
+) -> impl Responder {
+    let store = store.lock().unwrap();
+    let buckets = match store.aggregate_window(
+        req.start_time,
+        req.end_time,
+        req.bucket_seconds,
+        &req.metrics,
+    ) {
+        Ok(buckets) => buckets,
+        Err(e) => {
+            return web::Json(json!({
+                "error": format!("Failed to aggregate history: {}", e)
+            }))
+        }
+    };
+
+    web::Json(json!({
+        "buckets": buckets.iter().map(|b| json!({
+            "bucket_start": b.bucket_start.to_rfc3339(),
+            "metrics": {
+                "latency_ms": b.averages.latency,
+                "jitter_ms": b.averages.jitter,
+                "packet_loss_percent": b.averages.packet_loss,
+                "signal_strength_percent": b.averages.signal_strength,
+                "download_speed_mbps": b.averages.download_speed,
+                "upload_speed_mbps": b.averages.upload_speed,
+            }
+        })).collect::<Vec<_>>()
     }))
 }
+
+#[get("/top-talkers")]
+async fn top_talkers() -> impl Responder {
+    let ranking = web::block(|| crate::top_talkers::rank_processes(std::time::Duration::from_millis(500)))
+        .await
+        .unwrap_or_default();
+
+    web::Json(json!({ "processes": ranking }))
+}
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
 /// Primary response model for the dashboard
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct NetworkHealthResponse {
     pub status: NetworkStatus,
     pub metrics: NetworkMetrics,
@@ -12,7 +12,7 @@ pub struct NetworkHealthResponse {
 }
 
 /// Detailed network metrics (your 7 core fields)
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkMetrics {
     pub latency_ms: Option<f64>,
     pub jitter_ms: Option<f64>,
@@ -24,7 +24,7 @@ pub struct NetworkMetrics {
 }
 
 /// Health status classification
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum NetworkStatus {
     Excellent,
@@ -35,7 +35,7 @@ pub enum NetworkStatus {
 }
 
 /// Currently active optimizations
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ActiveOptimization {
     pub name: String,
     pub description: String,
@@ -44,7 +44,7 @@ pub struct ActiveOptimization {
 }
 
 /// Optimization impact assessment
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum OptimizationImpact {
     Positive,
@@ -57,10 +57,16 @@ pub enum OptimizationImpact {
 pub struct HistoricalDataRequest {
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
+    #[serde(default = "default_bucket_seconds")]
+    pub bucket_seconds: i64,
     #[serde(default = "default_metrics")]
     pub metrics: Vec<String>,  // e.g. ["latency_ms", "packet_loss_percent"]
 }
 
+fn default_bucket_seconds() -> i64 {
+    3600
+}
+
 fn default_metrics() -> Vec<String> {
     vec![
         "latency_ms".into(),
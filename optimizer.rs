@@ -1,17 +1,45 @@
 use serde::Serialize;
-use crate::probe_data::{NetworkHealth, HealthStatus};
+use crate::config::Config;
+use crate::models::{ActiveOptimization, OptimizationImpact};
+use crate::probe_data::{Averages, NetworkHealth, HealthStatus};
+use chrono::Utc;
 use std::process::Command;
-use sysinfo::{System, SystemExt, ProcessExt};
+use std::time::Duration;
+
+/// How long to wait after firing an optimization before re-sampling to check
+/// whether its target metric actually improved.
+const SETTLE_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// An optimization that can be verified: it targets one metric, and can be
+/// undone if that metric doesn't improve beyond `MARGIN_RATIO`.
+struct Optimization {
+    name: &'static str,
+    metric: fn(&Averages) -> Option<f64>,
+    lower_is_better: bool,
+    apply: fn(&NetworkOptimizer) -> Result<(), String>,
+    undo: fn(&NetworkOptimizer) -> Result<(), String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FailedOptimization {
+    pub name: String,
+    pub reason: String,
+    pub before: Option<f64>,
+    pub after: Option<f64>,
+}
 
 #[derive(Serialize)]
 pub struct NetworkOptimizer {
-    current_optimizations: Vec<String>,
-    failed_optimizations: Vec<String>,
+    #[serde(skip)]
+    config: Config,
+    current_optimizations: Vec<ActiveOptimization>,
+    failed_optimizations: Vec<FailedOptimization>,
 }
 
 impl NetworkOptimizer {
-    pub fn new() -> Self {
+    pub fn new(config: Config) -> Self {
         Self {
+            config,
             current_optimizations: Vec::new(),
             failed_optimizations: Vec::new(),
         }
@@ -33,73 +61,115 @@ impl NetworkOptimizer {
         Ok(())
     }
 
-    pub fn apply_optimizations(&mut self, health: &NetworkHealth) {
+    /// Applies the optimizations appropriate for `health`, verifying each
+    /// against a fresh sample from `resample` and rolling back any whose
+    /// target metric did not improve beyond `MARGIN_RATIO`.
+    pub fn apply_optimizations(
+        &mut self,
+        health: &NetworkHealth,
+        resample: &mut dyn FnMut() -> Option<Averages>,
+    ) {
         self.current_optimizations.clear();
         self.failed_optimizations.clear();
 
         match health.status {
-            HealthStatus::Critical => self.handle_critical(health),
-            HealthStatus::Poor => self.handle_poor(health),
-            HealthStatus::Fair => self.handle_fair(health),
-            _ => if let Err(e) = self.maintain_good_state() {
-                self.failed_optimizations.push(e);
-            },
-        }
-    }
-
-    fn handle_critical(&mut self, health: &NetworkHealth) {
-        if health.averages.packet_loss.unwrap_or(0.0) > 5.0 {
-            match self.switch_to_backup_connection() {
-                Ok(_) => self.current_optimizations.push(
-                    "Switched to backup connection".to_string(),
-                ),
-                Err(e) => self.failed_optimizations.push(e),
+            HealthStatus::Critical => self.handle_critical(health, resample),
+            HealthStatus::Poor => self.handle_poor(health, resample),
+            HealthStatus::Fair => self.handle_fair(health, resample),
+            _ => {
+                if let Err(e) = self.maintain_good_state() {
+                    self.failed_optimizations.push(FailedOptimization {
+                        name: "Maintain good state".to_string(),
+                        reason: e,
+                        before: None,
+                        after: None,
+                    });
+                }
             }
         }
-        
-        if health.averages.latency.unwrap_or(0.0) > 150.0 {
-            match self.enable_aggressive_qos() {
-                Ok(_) => self.current_optimizations.push(
-                    "Enabled aggressive QoS".to_string(),
-                ),
-                Err(e) => self.failed_optimizations.push(e),
-            }
+    }
+
+    fn handle_critical(&mut self, health: &NetworkHealth, resample: &mut dyn FnMut() -> Option<Averages>) {
+        if health.averages.packet_loss.unwrap_or(0.0) > self.config.thresholds.packet_loss_percent {
+            self.run_verified(&BACKUP_CONNECTION, health, resample);
+        }
+
+        if health.averages.latency.unwrap_or(0.0) > self.config.thresholds.latency_ms {
+            self.run_verified(&AGGRESSIVE_QOS, health, resample);
         }
-        
+
         if let Err(e) = self.restart_network_services() {
-            self.failed_optimizations.push(e);
+            self.failed_optimizations.push(FailedOptimization {
+                name: "Restart network services".to_string(),
+                reason: e,
+                before: None,
+                after: None,
+            });
         }
     }
 
-    fn handle_poor(&mut self, health: &NetworkHealth) {
-        if health.averages.signal_strength.unwrap_or(100.0) < 50.0 {
-            match self.adjust_wireless_power() {
-                Ok(_) => self.current_optimizations.push(
-                    "Adjusted wireless power".to_string(),
-                ),
-                Err(e) => self.failed_optimizations.push(e),
-            }
+    fn handle_poor(&mut self, health: &NetworkHealth, resample: &mut dyn FnMut() -> Option<Averages>) {
+        if health.averages.signal_strength.unwrap_or(100.0) < self.config.thresholds.signal_strength_percent {
+            self.run_verified(&WIRELESS_POWER, health, resample);
         }
-        
-        if health.averages.download_speed.unwrap_or(0.0) < 10.0 {
-            match self.limit_bandwidth_hogs() {
-                Ok(_) => self.current_optimizations.push(
-                    "Limited bandwidth hogs".to_string(),
-                ),
-                Err(e) => self.failed_optimizations.push(e),
-            }
+
+        if health.averages.download_speed.unwrap_or(0.0) < self.config.thresholds.download_speed_mbps {
+            self.run_verified(&BANDWIDTH_HOGS, health, resample);
         }
     }
 
-    fn handle_fair(&mut self, health: &NetworkHealth) {
-        if health.averages.jitter.unwrap_or(0.0) > 10.0 {
-            match self.enable_jitter_buffering() {
-                Ok(_) => self.current_optimizations.push(
-                    "Enabled jitter buffering".to_string(),
-                ),
-                Err(e) => self.failed_optimizations.push(e),
-            }
+    fn handle_fair(&mut self, health: &NetworkHealth, resample: &mut dyn FnMut() -> Option<Averages>) {
+        if health.averages.jitter.unwrap_or(0.0) > self.config.thresholds.jitter_ms {
+            self.run_verified(&JITTER_BUFFERING, health, resample);
+        }
+    }
+
+    /// Snapshots the triggering metric, applies the optimization, waits for
+    /// things to settle, then re-samples and compares. Rolls back (and
+    /// records in `failed_optimizations`) anything that didn't improve
+    /// beyond `MARGIN_RATIO`. If either sample lacks the metric, the impact is
+    /// `Neutral` and nothing is rolled back.
+    fn run_verified(
+        &mut self,
+        opt: &Optimization,
+        health: &NetworkHealth,
+        resample: &mut dyn FnMut() -> Option<Averages>,
+    ) {
+        let before = (opt.metric)(&health.averages);
+
+        if let Err(e) = (opt.apply)(self) {
+            self.failed_optimizations.push(FailedOptimization {
+                name: opt.name.to_string(),
+                reason: e,
+                before,
+                after: None,
+            });
+            return;
+        }
+
+        std::thread::sleep(SETTLE_INTERVAL);
+        let after = resample().and_then(|a| (opt.metric)(&a));
+        let impact = classify_impact(before, after, opt.lower_is_better);
+
+        if impact == OptimizationImpact::Negative {
+            let undo_result = (opt.undo)(self);
+            self.failed_optimizations.push(FailedOptimization {
+                name: opt.name.to_string(),
+                reason: match undo_result {
+                    Ok(_) => "Metric did not improve beyond margin; rolled back".to_string(),
+                    Err(e) => format!("Metric did not improve beyond margin; rollback also failed: {e}"),
+                },
+                before,
+                after,
+            });
         }
+
+        self.current_optimizations.push(ActiveOptimization {
+            name: opt.name.to_string(),
+            description: opt.name.to_string(),
+            implemented_at: Utc::now(),
+            impact,
+        });
     }
 
     fn maintain_good_state(&self) -> Result<(), String> {
@@ -109,7 +179,24 @@ impl NetworkOptimizer {
     fn switch_to_backup_connection(&self) -> Result<(), String> {
         #[cfg(target_os = "linux")]
         {
-            self.execute_system_command("nmcli", &["connection", "up", "backup-connection"])
+            self.execute_system_command(
+                "nmcli",
+                &["connection", "up", &self.config.interfaces.backup_connection],
+            )
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(())
+        }
+    }
+
+    fn undo_backup_connection(&self) -> Result<(), String> {
+        #[cfg(target_os = "linux")]
+        {
+            self.execute_system_command(
+                "nmcli",
+                &["connection", "down", &self.config.interfaces.backup_connection],
+            )
         }
         #[cfg(not(target_os = "linux"))]
         {
@@ -120,7 +207,24 @@ impl NetworkOptimizer {
     fn enable_aggressive_qos(&self) -> Result<(), String> {
         #[cfg(target_os = "linux")]
         {
-            self.execute_system_command("tc", &["qdisc", "add", "dev", "eth0", "root", "htb"])
+            self.execute_system_command(
+                "tc",
+                &["qdisc", "add", "dev", &self.config.interfaces.primary, "root", "htb"],
+            )
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(())
+        }
+    }
+
+    fn undo_aggressive_qos(&self) -> Result<(), String> {
+        #[cfg(target_os = "linux")]
+        {
+            self.execute_system_command(
+                "tc",
+                &["qdisc", "del", "dev", &self.config.interfaces.primary, "root"],
+            )
         }
         #[cfg(not(target_os = "linux"))]
         {
@@ -131,7 +235,24 @@ impl NetworkOptimizer {
     fn adjust_wireless_power(&self) -> Result<(), String> {
         #[cfg(target_os = "linux")]
         {
-            self.execute_system_command("iwconfig", &["wlan0", "txpower", "20"])
+            self.execute_system_command(
+                "iwconfig",
+                &[&self.config.interfaces.wireless, "txpower", "20"],
+            )
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(())
+        }
+    }
+
+    fn undo_wireless_power(&self) -> Result<(), String> {
+        #[cfg(target_os = "linux")]
+        {
+            self.execute_system_command(
+                "iwconfig",
+                &[&self.config.interfaces.wireless, "txpower", "auto"],
+            )
         }
         #[cfg(not(target_os = "linux"))]
         {
@@ -140,17 +261,22 @@ impl NetworkOptimizer {
     }
 
     fn limit_bandwidth_hogs(&self) -> Result<(), String> {
-        let mut sys = System::new();
-        sys.refresh_all();
-        
-        for (pid, process) in sys.processes() {
-            if process.disk_usage().total_read_bytes > 100_000_000 {
-                self.execute_system_command("renice", &["19", &pid.to_string()])?;
+        let ranking = crate::top_talkers::rank_processes(Duration::from_millis(500));
+
+        for process in ranking {
+            if process.download_mbps + process.upload_mbps > self.config.thresholds.bandwidth_hog_mbps {
+                self.execute_system_command("renice", &["19", &process.pid.to_string()])?;
             }
         }
         Ok(())
     }
 
+    fn undo_bandwidth_hogs(&self) -> Result<(), String> {
+        // Reniced processes drift back to the default priority on their own;
+        // nothing to actively restore.
+        Ok(())
+    }
+
     fn enable_jitter_buffering(&self) -> Result<(), String> {
         #[cfg(target_os = "linux")]
         {
@@ -162,6 +288,17 @@ impl NetworkOptimizer {
         }
     }
 
+    fn undo_jitter_buffering(&self) -> Result<(), String> {
+        #[cfg(target_os = "linux")]
+        {
+            self.execute_system_command("sh", &["-c", "echo 0 > /proc/sys/net/ipv4/tcp_low_latency"])
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(())
+        }
+    }
+
     fn restart_network_services(&self) -> Result<(), String> {
         #[cfg(target_os = "linux")]
         {
@@ -184,11 +321,134 @@ impl NetworkOptimizer {
         }
     }
 
-    pub fn get_current_optimizations(&self) -> &Vec<String> {
+    pub fn get_current_optimizations(&self) -> &Vec<ActiveOptimization> {
         &self.current_optimizations
     }
 
-    pub fn get_failed_optimizations(&self) -> &Vec<String> {
+    pub fn get_failed_optimizations(&self) -> &Vec<FailedOptimization> {
         &self.failed_optimizations
     }
 }
+
+/// An optimization "improved" its metric if the post-settle sample moved by
+/// at least this fraction (or 0.01 absolute, whichever is larger) in the
+/// right direction. Anything else — including no measurable change — counts
+/// as not having helped.
+const MARGIN_RATIO: f64 = 0.05;
+
+fn classify_impact(before: Option<f64>, after: Option<f64>, lower_is_better: bool) -> OptimizationImpact {
+    match (before, after) {
+        (Some(before), Some(after)) => {
+            let margin = (before.abs() * MARGIN_RATIO).max(0.01);
+            let improved = if lower_is_better {
+                after <= before - margin
+            } else {
+                after >= before + margin
+            };
+            if improved {
+                OptimizationImpact::Positive
+            } else {
+                OptimizationImpact::Negative
+            }
+        }
+        _ => OptimizationImpact::Neutral,
+    }
+}
+
+fn metric_packet_loss(a: &Averages) -> Option<f64> {
+    a.packet_loss
+}
+
+fn metric_latency(a: &Averages) -> Option<f64> {
+    a.latency
+}
+
+fn metric_signal_strength(a: &Averages) -> Option<f64> {
+    a.signal_strength
+}
+
+fn metric_download_speed(a: &Averages) -> Option<f64> {
+    a.download_speed
+}
+
+fn metric_jitter(a: &Averages) -> Option<f64> {
+    a.jitter
+}
+
+const BACKUP_CONNECTION: Optimization = Optimization {
+    name: "Switched to backup connection",
+    metric: metric_packet_loss,
+    lower_is_better: true,
+    apply: NetworkOptimizer::switch_to_backup_connection,
+    undo: NetworkOptimizer::undo_backup_connection,
+};
+
+const AGGRESSIVE_QOS: Optimization = Optimization {
+    name: "Enabled aggressive QoS",
+    metric: metric_latency,
+    lower_is_better: true,
+    apply: NetworkOptimizer::enable_aggressive_qos,
+    undo: NetworkOptimizer::undo_aggressive_qos,
+};
+
+const WIRELESS_POWER: Optimization = Optimization {
+    name: "Adjusted wireless power",
+    metric: metric_signal_strength,
+    lower_is_better: false,
+    apply: NetworkOptimizer::adjust_wireless_power,
+    undo: NetworkOptimizer::undo_wireless_power,
+};
+
+const BANDWIDTH_HOGS: Optimization = Optimization {
+    name: "Limited bandwidth hogs",
+    metric: metric_download_speed,
+    lower_is_better: false,
+    apply: NetworkOptimizer::limit_bandwidth_hogs,
+    undo: NetworkOptimizer::undo_bandwidth_hogs,
+};
+
+const JITTER_BUFFERING: Optimization = Optimization {
+    name: "Enabled jitter buffering",
+    metric: metric_jitter,
+    lower_is_better: true,
+    apply: NetworkOptimizer::enable_jitter_buffering,
+    undo: NetworkOptimizer::undo_jitter_buffering,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_impact_positive_when_lower_is_better_and_it_dropped_past_margin() {
+        // margin = max(100.0 * 0.05, 0.01) = 5.0, so 94 clears 100 - 5
+        let impact = classify_impact(Some(100.0), Some(94.0), true);
+        assert_eq!(impact, OptimizationImpact::Positive);
+    }
+
+    #[test]
+    fn classify_impact_negative_when_within_margin() {
+        let impact = classify_impact(Some(100.0), Some(97.0), true);
+        assert_eq!(impact, OptimizationImpact::Negative);
+    }
+
+    #[test]
+    fn classify_impact_positive_when_higher_is_better_and_it_rose_past_margin() {
+        let impact = classify_impact(Some(10.0), Some(11.0), false);
+        assert_eq!(impact, OptimizationImpact::Positive);
+    }
+
+    #[test]
+    fn classify_impact_uses_absolute_floor_for_small_values() {
+        // before=0.1 -> ratio margin is 0.005, floored to 0.01
+        let impact = classify_impact(Some(0.1), Some(0.095), true);
+        assert_eq!(impact, OptimizationImpact::Negative);
+    }
+
+    #[test]
+    fn classify_impact_neutral_when_either_sample_missing() {
+        assert_eq!(classify_impact(None, Some(1.0), true), OptimizationImpact::Neutral);
+        assert_eq!(classify_impact(Some(1.0), None, true), OptimizationImpact::Neutral);
+        assert_eq!(classify_impact(None, None, true), OptimizationImpact::Neutral);
+    }
+}
@@ -1,4 +1,4 @@
-//use rusqlite::{params, Connection};
+use rusqlite::{params, Connection};
 use serde::{Deserialize, Deserializer};
 
 #[allow(dead_code)]
@@ -44,7 +44,7 @@ pub struct NetworkHealth {
     pub status: HealthStatus,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum HealthStatus {
     Excellent,
     Good,
@@ -111,7 +111,7 @@ pub fn filter_last_n_days(data: &[ProbeData], days: i64) -> Vec<ProbeData> {
         .collect()
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Averages {
     pub latency: Option<f64>,
     pub jitter: Option<f64>,
@@ -148,15 +148,406 @@ pub fn calculate_averages(data: &[ProbeData]) -> Averages {
     }
 }
 
-pub fn determine_health(averages: &Averages) -> HealthStatus {
-    // Implement your health determination logic
-    if averages.packet_loss.unwrap_or(0.0) > 5.0 || averages.latency.unwrap_or(0.0) > 150.0 {
+pub fn determine_health(averages: &Averages, thresholds: &crate::config::Thresholds) -> HealthStatus {
+    if averages.packet_loss.unwrap_or(0.0) > thresholds.packet_loss_percent
+        || averages.latency.unwrap_or(0.0) > thresholds.latency_ms
+    {
         HealthStatus::Critical
-    } else if averages.signal_strength.unwrap_or(100.0) < 50.0 || averages.download_speed.unwrap_or(0.0) < 10.0 {
+    } else if averages.signal_strength.unwrap_or(100.0) < thresholds.signal_strength_percent
+        || averages.download_speed.unwrap_or(0.0) < thresholds.download_speed_mbps
+    {
         HealthStatus::Poor
-    } else if averages.jitter.unwrap_or(0.0) > 10.0 {
+    } else if averages.jitter.unwrap_or(0.0) > thresholds.jitter_ms {
         HealthStatus::Fair
     } else {
         HealthStatus::Good
     }
 }
+
+// --- Live sampling from /proc, for machines with no pre-existing data.csv ---
+
+use std::collections::HashMap;
+use std::fs;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, Default)]
+struct IfaceCounters {
+    rx_bytes: u64,
+    tx_bytes: u64,
+    rx_packets: u64,
+    tx_packets: u64,
+    rx_errors: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct UdpCounters {
+    in_datagrams: u64,
+    out_datagrams: u64,
+    in_errors: u64,
+    rcvbuf_errors: u64,
+}
+
+struct RawSample {
+    at: Instant,
+    iface: IfaceCounters,
+    udp: UdpCounters,
+}
+
+/// Polls kernel counters for a single interface on a fixed interval and derives
+/// live `Averages` from the deltas, so the dashboard works without a CSV.
+pub struct LiveSampler {
+    interface: String,
+    interval: Duration,
+    last: Option<RawSample>,
+}
+
+impl LiveSampler {
+    pub fn new(interface: impl Into<String>, interval: Duration) -> Self {
+        Self {
+            interface: interface.into(),
+            interval,
+            last: None,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Takes a fresh reading and returns the derived averages, or `None` if
+    /// this is the first sample (no predecessor to diff against) or the
+    /// counters went backwards (wrap-around / interface reset).
+    pub fn sample(&mut self) -> Result<Option<Averages>, Box<dyn std::error::Error>> {
+        let iface = read_iface_counters(&self.interface)?;
+        let udp = read_udp_counters()?;
+        let now = Instant::now();
+
+        let averages = match &self.last {
+            None => None,
+            Some(prev) => {
+                let elapsed = now.duration_since(prev.at).as_secs_f64();
+                derive_averages(&prev.iface, &iface, &prev.udp, &udp, elapsed)
+            }
+        };
+
+        self.last = Some(RawSample { at: now, iface, udp });
+        Ok(averages)
+    }
+}
+
+fn derive_averages(
+    prev_iface: &IfaceCounters,
+    iface: &IfaceCounters,
+    prev_udp: &UdpCounters,
+    udp: &UdpCounters,
+    elapsed_secs: f64,
+) -> Option<Averages> {
+    if elapsed_secs <= 0.0 {
+        return None;
+    }
+
+    let rx_bytes_delta = checked_delta(prev_iface.rx_bytes, iface.rx_bytes)?;
+    let tx_bytes_delta = checked_delta(prev_iface.tx_bytes, iface.tx_bytes)?;
+    let rx_packets_delta = checked_delta(prev_iface.rx_packets, iface.rx_packets)?;
+    let rx_errors_delta = checked_delta(prev_iface.rx_errors, iface.rx_errors)?;
+    let _udp_in_delta = checked_delta(prev_udp.in_datagrams, udp.in_datagrams)?;
+    let _udp_out_delta = checked_delta(prev_udp.out_datagrams, udp.out_datagrams)?;
+    let _udp_errors_delta = checked_delta(prev_udp.in_errors, udp.in_errors)?;
+    let _udp_rcvbuf_delta = checked_delta(prev_udp.rcvbuf_errors, udp.rcvbuf_errors)?;
+
+    let download_speed = Some(mbps(rx_bytes_delta, elapsed_secs));
+    let upload_speed = Some(mbps(tx_bytes_delta, elapsed_secs));
+    let packet_loss = if rx_packets_delta > 0 {
+        Some((rx_errors_delta as f64 / rx_packets_delta as f64) * 100.0)
+    } else {
+        None
+    };
+
+    Some(Averages {
+        // Not derivable from /proc/net counters; left for other probes to fill.
+        latency: None,
+        jitter: None,
+        packet_loss,
+        signal_strength: None,
+        download_speed,
+        upload_speed,
+    })
+}
+
+fn checked_delta(prev: u64, current: u64) -> Option<u64> {
+    current.checked_sub(prev)
+}
+
+fn mbps(bytes_delta: u64, elapsed_secs: f64) -> f64 {
+    (bytes_delta as f64 * 8.0) / elapsed_secs / 1_000_000.0
+}
+
+fn read_iface_counters(interface: &str) -> Result<IfaceCounters, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string("/proc/net/dev")?;
+    for line in contents.lines().skip(2) {
+        let Some((name, rest)) = line.split_once(':') else {
+            continue;
+        };
+        if name.trim() != interface {
+            continue;
+        }
+        let fields: Vec<u64> = rest
+            .split_whitespace()
+            .map(|f| f.parse::<u64>().unwrap_or(0))
+            .collect();
+        // /proc/net/dev columns: rx bytes packets errs drop fifo frame compressed multicast
+        //                        tx bytes packets errs drop fifo colls carrier compressed
+        if fields.len() < 16 {
+            return Err(format!("unexpected /proc/net/dev format for {interface}").into());
+        }
+        return Ok(IfaceCounters {
+            rx_bytes: fields[0],
+            rx_packets: fields[1],
+            rx_errors: fields[2],
+            tx_bytes: fields[8],
+            tx_packets: fields[9],
+        });
+    }
+    Err(format!("interface {interface} not found in /proc/net/dev").into())
+}
+
+fn read_udp_counters() -> Result<UdpCounters, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string("/proc/net/snmp")?;
+    let mut lines = contents.lines();
+    while let Some(header) = lines.next() {
+        if !header.starts_with("Udp:") {
+            continue;
+        }
+        let Some(values) = lines.next() else {
+            break;
+        };
+        let headers: Vec<&str> = header.split_whitespace().skip(1).collect();
+        let values: Vec<&str> = values.split_whitespace().skip(1).collect();
+        let by_name: HashMap<&str, u64> = headers
+            .into_iter()
+            .zip(values)
+            .map(|(k, v)| (k, v.parse::<u64>().unwrap_or(0)))
+            .collect();
+        return Ok(UdpCounters {
+            in_datagrams: *by_name.get("InDatagrams").unwrap_or(&0),
+            out_datagrams: *by_name.get("OutDatagrams").unwrap_or(&0),
+            in_errors: *by_name.get("InErrors").unwrap_or(&0),
+            rcvbuf_errors: *by_name.get("RcvbufErrors").unwrap_or(&0),
+        });
+    }
+    Err("Udp section not found in /proc/net/snmp".into())
+}
+
+// --- SQLite persistence and windowed historical aggregation ---
+
+use chrono::{DateTime, Utc};
+
+/// One row per sample, so historical windows can be re-aggregated on demand
+/// instead of only ever looking at the last 3 days.
+pub struct Store {
+    conn: Connection,
+}
+
+/// Per-metric average for a fixed time slice of an aggregation window.
+#[derive(Debug, Clone)]
+pub struct HistoryBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub averages: Averages,
+}
+
+impl Store {
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS samples (
+                ts              TEXT NOT NULL,
+                latency         REAL,
+                jitter          REAL,
+                packet_loss     REAL,
+                signal_strength REAL,
+                download_speed  REAL,
+                upload_speed    REAL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_samples_ts ON samples(ts)",
+            [],
+        )?;
+        Ok(Self { conn })
+    }
+
+    pub fn insert_sample(&self, ts: DateTime<Utc>, averages: &Averages) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO samples (ts, latency, jitter, packet_loss, signal_strength, download_speed, upload_speed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                ts.to_rfc3339(),
+                averages.latency,
+                averages.jitter,
+                averages.packet_loss,
+                averages.signal_strength,
+                averages.download_speed,
+                averages.upload_speed,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Averages `metrics` into fixed `bucket_seconds` slices over `[start, end)`.
+    /// Only rows strictly before `end` are included, so repeated queries over
+    /// the same window are deterministic.
+    pub fn aggregate_window(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket_seconds: i64,
+        metrics: &[String],
+    ) -> rusqlite::Result<Vec<HistoryBucket>> {
+        if bucket_seconds <= 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT ts, latency, jitter, packet_loss, signal_strength, download_speed, upload_speed
+             FROM samples
+             WHERE ts >= ?1 AND ts < ?2
+             ORDER BY ts ASC",
+        )?;
+        let rows = stmt.query_map(params![start.to_rfc3339(), end.to_rfc3339()], |row| {
+            let ts: String = row.get(0)?;
+            Ok((
+                ts,
+                row.get::<_, Option<f64>>(1)?,
+                row.get::<_, Option<f64>>(2)?,
+                row.get::<_, Option<f64>>(3)?,
+                row.get::<_, Option<f64>>(4)?,
+                row.get::<_, Option<f64>>(5)?,
+                row.get::<_, Option<f64>>(6)?,
+            ))
+        })?;
+
+        // Round up so a trailing slice shorter than `bucket_seconds` still
+        // gets its own bucket instead of its rows being dropped.
+        let window_secs = (end - start).num_seconds();
+        let bucket_count = ((window_secs + bucket_seconds - 1) / bucket_seconds).max(0) as usize;
+        let mut sums = vec![[0.0f64; 6]; bucket_count];
+        let mut counts = vec![[0u32; 6]; bucket_count];
+
+        for row in rows {
+            let (ts, latency, jitter, packet_loss, signal_strength, download_speed, upload_speed) =
+                row?;
+            let Ok(ts) = DateTime::parse_from_rfc3339(&ts) else {
+                continue;
+            };
+            let ts = ts.with_timezone(&Utc);
+            let offset_secs = (ts - start).num_seconds();
+            if offset_secs < 0 {
+                continue;
+            }
+            let bucket = (offset_secs / bucket_seconds) as usize;
+            if bucket >= bucket_count {
+                continue;
+            }
+
+            for (i, value) in [latency, jitter, packet_loss, signal_strength, download_speed, upload_speed]
+                .into_iter()
+                .enumerate()
+            {
+                if let Some(value) = value {
+                    sums[bucket][i] += value;
+                    counts[bucket][i] += 1;
+                }
+            }
+        }
+
+        let wanted: std::collections::HashSet<&str> =
+            metrics.iter().map(String::as_str).collect();
+
+        let buckets = (0..bucket_count)
+            .map(|i| {
+                let avg = |idx: usize, name: &str| {
+                    if !wanted.contains(name) || counts[i][idx] == 0 {
+                        None
+                    } else {
+                        Some(sums[i][idx] / counts[i][idx] as f64)
+                    }
+                };
+                HistoryBucket {
+                    bucket_start: start + chrono::Duration::seconds(i as i64 * bucket_seconds),
+                    averages: Averages {
+                        latency: avg(0, "latency_ms"),
+                        jitter: avg(1, "jitter_ms"),
+                        packet_loss: avg(2, "packet_loss_percent"),
+                        signal_strength: avg(3, "signal_strength_percent"),
+                        download_speed: avg(4, "download_speed_mbps"),
+                        upload_speed: avg(5, "upload_speed_mbps"),
+                    },
+                }
+            })
+            .collect();
+
+        Ok(buckets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_averages_computes_speed_and_loss_from_deltas() {
+        let prev = IfaceCounters {
+            rx_bytes: 1_000,
+            tx_bytes: 500,
+            rx_packets: 100,
+            tx_packets: 50,
+            rx_errors: 0,
+        };
+        let iface = IfaceCounters {
+            rx_bytes: 1_000 + 1_000_000,
+            tx_bytes: 500 + 500_000,
+            rx_packets: 200,
+            tx_packets: 50,
+            rx_errors: 2,
+        };
+        let udp = UdpCounters::default();
+
+        let averages = derive_averages(&prev, &iface, &udp, &udp, 1.0).unwrap();
+
+        assert_eq!(averages.download_speed, Some(mbps(1_000_000, 1.0)));
+        assert_eq!(averages.upload_speed, Some(mbps(500_000, 1.0)));
+        assert_eq!(averages.packet_loss, Some(2.0));
+        assert_eq!(averages.latency, None);
+        assert_eq!(averages.jitter, None);
+        assert_eq!(averages.signal_strength, None);
+    }
+
+    #[test]
+    fn derive_averages_reports_no_packet_loss_without_packets() {
+        let prev = IfaceCounters::default();
+        let iface = IfaceCounters { rx_bytes: 100, ..Default::default() };
+        let udp = UdpCounters::default();
+
+        let averages = derive_averages(&prev, &iface, &udp, &udp, 1.0).unwrap();
+
+        assert_eq!(averages.packet_loss, None);
+    }
+
+    #[test]
+    fn derive_averages_none_when_counters_wrap() {
+        let prev = IfaceCounters { rx_bytes: 1_000, ..Default::default() };
+        let iface = IfaceCounters { rx_bytes: 0, ..Default::default() };
+        let udp = UdpCounters::default();
+
+        assert!(derive_averages(&prev, &iface, &udp, &udp, 1.0).is_none());
+    }
+
+    #[test]
+    fn derive_averages_none_when_no_time_elapsed() {
+        let iface = IfaceCounters::default();
+        let udp = UdpCounters::default();
+
+        assert!(derive_averages(&iface, &iface, &udp, &udp, 0.0).is_none());
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::process::Command;
+use std::time::Duration;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProcessBandwidth {
+    pub pid: u32,
+    pub process_name: String,
+    pub download_mbps: f64,
+    pub upload_mbps: f64,
+}
+
+/// Ranks processes by bandwidth use by shelling out to `nethogs` in trace
+/// mode and parsing its per-process throughput lines.
+///
+/// This used to diff `/proc/net/{tcp,udp}` queue depths between two samples,
+/// but `tx_queue:rx_queue` (per `proc(5)`) is the byte count currently
+/// sitting in a socket's send/receive queue at that instant, not a
+/// cumulative transfer counter — a connection streaming megabytes but
+/// draining its queues instantly reads near-zero, while an idle connection
+/// with unread data sitting in its queue reads as high "throughput". Real
+/// attribution needs packet capture or eBPF, which is what `nethogs`
+/// already does, so this shells out to it instead of reimplementing it badly.
+pub fn rank_processes(interval: Duration) -> Vec<ProcessBandwidth> {
+    let delay_secs = interval.as_secs().max(1).to_string();
+    let output = Command::new("nethogs")
+        .args(["-t", "-d", &delay_secs, "-c", "2"])
+        .output();
+
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            eprintln!(
+                "nethogs exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            return Vec::new();
+        }
+        Err(e) => {
+            eprintln!("Failed to run nethogs: {e}");
+            return Vec::new();
+        }
+    };
+
+    parse_nethogs_trace(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses `nethogs -t` output: each refresh prints one tab-separated line per
+/// process as `path/pid/uid\tsent_KBps\trecv_KBps`. Later refreshes overwrite
+/// earlier ones per pid, so what's left after scanning the whole trace is
+/// each process's most recent reading.
+fn parse_nethogs_trace(output: &str) -> Vec<ProcessBandwidth> {
+    let mut latest_by_pid: HashMap<u32, ProcessBandwidth> = HashMap::new();
+
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 3 {
+            continue;
+        }
+        let Some((process_name, pid)) = parse_program_pid(fields[0]) else {
+            continue;
+        };
+        let (Ok(sent_kbps), Ok(recv_kbps)) =
+            (fields[1].trim().parse::<f64>(), fields[2].trim().parse::<f64>())
+        else {
+            continue;
+        };
+
+        latest_by_pid.insert(
+            pid,
+            ProcessBandwidth {
+                pid,
+                process_name,
+                upload_mbps: kbps_to_mbps(sent_kbps),
+                download_mbps: kbps_to_mbps(recv_kbps),
+            },
+        );
+    }
+
+    let mut ranking: Vec<ProcessBandwidth> = latest_by_pid.into_values().collect();
+    ranking.sort_by(|a, b| {
+        (b.download_mbps + b.upload_mbps)
+            .partial_cmp(&(a.download_mbps + a.upload_mbps))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    ranking
+}
+
+/// Splits nethogs' `/path/to/program/pid/uid` identifier into the program
+/// path and pid.
+fn parse_program_pid(field: &str) -> Option<(String, u32)> {
+    let mut parts = field.rsplitn(3, '/');
+    let _uid = parts.next()?;
+    let pid = parts.next()?.parse().ok()?;
+    let path = parts.next()?.to_string();
+    Some((path, pid))
+}
+
+fn kbps_to_mbps(kbps: f64) -> f64 {
+    kbps * 8.0 / 1000.0
+}